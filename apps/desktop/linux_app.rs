@@ -5,23 +5,119 @@ use gtk4::prelude::*;
 use gtk4::{glib, Application, ApplicationWindow, Button, Box, TextView, Entry, Label, ProgressBar};
 use reqwest;
 use serde_json::{json, Value};
-use std::sync::Arc;
+use base64::Engine;
+use futures_util::StreamExt;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex as StdMutex};
+use tiktoken_rs::{get_bpe_from_model, CoreBPE};
 use tokio::sync::Mutex;
 
+// 模型的上下文容量上限（輸入 token 數），超過時需在送出前截斷
+const MAX_CONTEXT_TOKENS: usize = 4096;
+
+// OS 密鑰儲存區中用來存放 bearer token／refresh token 的服務與帳號名稱
+const KEYRING_SERVICE: &str = "modern-reader";
+const KEYRING_USER: &str = "session_token";
+const KEYRING_REFRESH_USER: &str = "refresh_token";
+
+// enhance_text_stream 透過 channel 送出的串流事件
+#[derive(Debug, Clone)]
+pub enum EnhanceStreamEvent {
+    Delta(String),
+    Total(usize),
+}
+
+// 將插圖的原始位元組解碼為可顯示的 Pixbuf
+fn load_pixbuf_from_bytes(bytes: &[u8]) -> Result<gtk4::gdk_pixbuf::Pixbuf, glib::Error> {
+    let bytes = glib::Bytes::from(bytes);
+    let stream = gtk4::gio::MemoryInputStream::from_bytes(&bytes);
+    gtk4::gdk_pixbuf::Pixbuf::from_stream(&stream, gtk4::gio::Cancellable::NONE)
+}
+
+// 取得目前的 Unix 時間戳，供歷史紀錄使用
+fn now_unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// 截斷方向：Start 捨棄開頭、保留結尾（留住最新內容）；End 捨棄結尾、保留開頭
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncationDirection {
+    Start,
+    End,
+}
+
 // MARK: - Modern Reader Linux SDK
 #[derive(Clone)]
 pub struct ModernReaderLinuxSDK {
     base_url: String,
+    model: String,
     session_token: Arc<Mutex<Option<String>>>,
+    refresh_token: Arc<Mutex<Option<String>>>,
     client: reqwest::Client,
+    bpe_cache: Arc<StdMutex<HashMap<String, Arc<CoreBPE>>>>,
 }
 
 impl ModernReaderLinuxSDK {
     pub fn new() -> Self {
         Self {
             base_url: "https://localhost:8443".to_string(),
+            model: "gpt-3.5-turbo".to_string(),
             session_token: Arc::new(Mutex::new(None)),
+            refresh_token: Arc::new(Mutex::new(None)),
             client: reqwest::Client::new(),
+            bpe_cache: Arc::new(StdMutex::new(HashMap::new())),
+        }
+    }
+
+    // 取得（並快取）目前模型的 BPE tokenizer
+    fn bpe(&self) -> Arc<CoreBPE> {
+        let mut cache = self.bpe_cache.lock().unwrap();
+        if let Some(bpe) = cache.get(&self.model) {
+            return bpe.clone();
+        }
+        let bpe = Arc::new(
+            get_bpe_from_model(&self.model).expect("未知的 tokenizer 模型"),
+        );
+        cache.insert(self.model.clone(), bpe.clone());
+        bpe
+    }
+
+    // 計算文本的 token 數量
+    pub fn count_tokens(&self, text: &str) -> usize {
+        self.bpe().encode_with_special_tokens(text).len()
+    }
+
+    // 將文本截斷至 max_tokens 以內，確保不會切到多位元組字元中間
+    pub fn truncate(&self, text: &str, max_tokens: usize, direction: TruncationDirection) -> String {
+        let bpe = self.bpe();
+        let ids = bpe.encode_with_special_tokens(text);
+        if ids.len() <= max_tokens {
+            return text.to_string();
+        }
+
+        let mut kept = match direction {
+            TruncationDirection::End => ids[..max_tokens].to_vec(),
+            TruncationDirection::Start => ids[ids.len() - max_tokens..].to_vec(),
+        };
+
+        // 截斷邊界落在多位元組字元中間時解碼會失敗：逐一捨棄邊界上的 token 直到能完整解碼
+        loop {
+            if let Ok(decoded) = bpe.decode(kept.clone()) {
+                return decoded;
+            }
+            if kept.is_empty() {
+                return String::new();
+            }
+            match direction {
+                TruncationDirection::End => { kept.pop(); }
+                TruncationDirection::Start => { kept.remove(0); }
+            }
         }
     }
 
@@ -40,12 +136,27 @@ impl ModernReaderLinuxSDK {
         };
 
         let response = self.make_request("/auth/login", Some(login_data)).await?;
-        
+
         if let Some(success) = response["success"].as_bool() {
             if success {
                 if let Some(token) = response["token"].as_str() {
-                    let mut session_token = self.session_token.lock().await;
-                    *session_token = Some(token.to_string());
+                    {
+                        let mut session_token = self.session_token.lock().await;
+                        *session_token = Some(token.to_string());
+                    }
+                    // 密鑰存儲（如 secret-service）可能在當前環境中不可用；
+                    // 持久化失敗不應讓已經成功的登入對 UI 顯示為失敗
+                    let _ = self.persist_session(token);
+
+                    // refresh token 是獨立於（較短效期的）access token 之外的憑證
+                    if let Some(refresh_token) = response["refresh_token"].as_str() {
+                        {
+                            let mut refresh_token_guard = self.refresh_token.lock().await;
+                            *refresh_token_guard = Some(refresh_token.to_string());
+                        }
+                        let _ = self.persist_refresh_token(refresh_token);
+                    }
+
                     return Ok(true);
                 }
             }
@@ -54,6 +165,103 @@ impl ModernReaderLinuxSDK {
         Ok(false)
     }
 
+    // 登出：清除本機 session 並通知伺服器
+    pub async fn logout(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let _ = self.make_request("/auth/logout", None).await;
+
+        {
+            let mut session_token = self.session_token.lock().await;
+            *session_token = None;
+        }
+        {
+            let mut refresh_token = self.refresh_token.lock().await;
+            *refresh_token = None;
+        }
+
+        if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER) {
+            let _ = entry.delete_password();
+        }
+        if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, KEYRING_REFRESH_USER) {
+            let _ = entry.delete_password();
+        }
+
+        Ok(())
+    }
+
+    // 將 bearer token 寫入作業系統的密鑰儲存區（libsecret/keyring）
+    pub fn persist_session(&self, token: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+        entry.set_password(token)?;
+        Ok(())
+    }
+
+    // 將 refresh token 寫入密鑰儲存區，與 access token 分開存放
+    pub fn persist_refresh_token(&self, refresh_token: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_REFRESH_USER)?;
+        entry.set_password(refresh_token)?;
+        Ok(())
+    }
+
+    // 從密鑰儲存區還原 session（access token 與 refresh token），供應用程式重啟後沿用
+    pub async fn restore_session(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+        let restored = match entry.get_password() {
+            Ok(token) => {
+                let mut session_token = self.session_token.lock().await;
+                *session_token = Some(token);
+                true
+            }
+            Err(_) => false,
+        };
+
+        if let Ok(refresh_entry) = keyring::Entry::new(KEYRING_SERVICE, KEYRING_REFRESH_USER) {
+            if let Ok(refresh_token) = refresh_entry.get_password() {
+                let mut refresh_token_guard = self.refresh_token.lock().await;
+                *refresh_token_guard = Some(refresh_token);
+            }
+        }
+
+        Ok(restored)
+    }
+
+    // 嘗試以 refresh token（非過期的 access token）換發新的 bearer token；成功時回傳 true
+    async fn refresh_session(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        let refresh_token = self.refresh_token.lock().await.clone();
+        let Some(refresh_token) = refresh_token else {
+            return Ok(false);
+        };
+
+        let url = format!("{}/auth/refresh", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&json!({ "refresh_token": refresh_token }))
+            .send()
+            .await?;
+        let json: Value = response.json().await?;
+
+        let Some(token) = json["token"].as_str() else {
+            return Ok(false);
+        };
+
+        {
+            let mut session_token = self.session_token.lock().await;
+            *session_token = Some(token.to_string());
+        }
+        let _ = self.persist_session(token);
+
+        // 伺服器可能會一併核發新的 refresh token（輪替）
+        if let Some(new_refresh_token) = json["refresh_token"].as_str() {
+            {
+                let mut refresh_token_guard = self.refresh_token.lock().await;
+                *refresh_token_guard = Some(new_refresh_token.to_string());
+            }
+            let _ = self.persist_refresh_token(new_refresh_token);
+        }
+
+        Ok(true)
+    }
+
     // AI 文本增強
     pub async fn enhance_text(&self, text: &str, style: &str) -> Result<String, Box<dyn std::error::Error>> {
         let request_data = json!({
@@ -63,13 +271,137 @@ impl ModernReaderLinuxSDK {
         });
 
         let response = self.make_request("/ai/enhance_text", Some(request_data)).await?;
-        
+
         Ok(response["enhanced_text"]
             .as_str()
             .unwrap_or("增強失敗")
             .to_string())
     }
 
+    // 多模型比較：同時以多個後端模型增強同一段文本，方便比較語氣差異
+    pub async fn enhance_text_multi(
+        &self,
+        text: &str,
+        style: &str,
+        models: &[&str],
+    ) -> Vec<(String, Result<String, String>)> {
+        let calls = models.iter().map(|model| {
+            let model = model.to_string();
+            async move {
+                let request_data = json!({
+                    "text": text,
+                    "style": style,
+                    "use_google": false,
+                    "model": model
+                });
+
+                let result = self
+                    .make_request("/ai/enhance_text", Some(request_data))
+                    .await
+                    .map(|response| {
+                        response["enhanced_text"]
+                            .as_str()
+                            .unwrap_or("增強失敗")
+                            .to_string()
+                    })
+                    .map_err(|err| err.to_string());
+
+                (model, result)
+            }
+        });
+
+        futures_util::future::join_all(calls).await
+    }
+
+    // 批次文件增強：將整份文件的段落以有界並行度增強，並依序回傳逐段落結果。
+    // 個別段落失敗不應讓已完成的其他段落一併被丟棄，因此回傳每段各自的 Result（與 enhance_text_multi 一致）
+    pub async fn enhance_document<F>(
+        &self,
+        paragraphs: Vec<String>,
+        style: &str,
+        progress: F,
+    ) -> Vec<Result<String, String>>
+    where
+        F: Fn(usize, usize),
+    {
+        const MAX_CONCURRENT_CHUNKS: usize = 4;
+
+        let total = paragraphs.len();
+        let semaphore = tokio::sync::Semaphore::new(MAX_CONCURRENT_CHUNKS);
+        let completed = StdMutex::new(0usize);
+
+        let tasks = paragraphs.iter().map(|paragraph| async {
+            let _permit = semaphore.acquire().await.expect("semaphore 已關閉");
+            let result = self.enhance_text(paragraph, style).await.map_err(|err| err.to_string());
+
+            let mut count = completed.lock().unwrap();
+            *count += 1;
+            progress(*count, total);
+            drop(count);
+
+            result
+        });
+
+        futures_util::future::join_all(tasks).await
+    }
+
+    // AI 文本增強（SSE 串流版本）：逐步把增強結果透過 channel 送出，讓 UI 即時顯示
+    pub async fn enhance_text_stream(
+        &self,
+        text: &str,
+        style: &str,
+        tx: tokio::sync::mpsc::UnboundedSender<EnhanceStreamEvent>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let url = format!("{}/ai/enhance_text", self.base_url);
+        let request_data = json!({
+            "text": text,
+            "style": style,
+            "use_google": false,
+            "stream": true
+        });
+
+        let mut response = self.send_stream_request(&url, &request_data).await?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED && self.refresh_session().await.unwrap_or(false) {
+            response = self.send_stream_request(&url, &request_data).await?;
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+
+        while let Some(chunk) = stream.next().await {
+            buffer.push_str(&String::from_utf8_lossy(&chunk?));
+
+            // 以空行分隔每個 SSE 事件
+            while let Some(pos) = buffer.find("\n\n") {
+                let event = buffer[..pos].to_string();
+                buffer.drain(..pos + 2);
+
+                for line in event.lines() {
+                    let Some(data) = line.strip_prefix("data:") else {
+                        continue;
+                    };
+                    let data = data.trim();
+                    if data == "[DONE]" {
+                        return Ok(());
+                    }
+
+                    if let Ok(payload) = serde_json::from_str::<Value>(data) {
+                        if let Some(delta) = payload["delta"].as_str() {
+                            let _ = tx.send(EnhanceStreamEvent::Delta(delta.to_string()));
+                        }
+                        if let Some(total) = payload["total_tokens"].as_u64() {
+                            let _ = tx.send(EnhanceStreamEvent::Total(total as usize));
+                        }
+                    } else if !data.is_empty() {
+                        let _ = tx.send(EnhanceStreamEvent::Delta(data.to_string()));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     // 情感分析
     pub async fn analyze_emotion(&self, text: &str) -> Result<(String, f64), Box<dyn std::error::Error>> {
         let request_data = json!({ "text": text });
@@ -86,6 +418,40 @@ impl ModernReaderLinuxSDK {
         Ok((emotion, confidence))
     }
 
+    // 文字轉插圖：依提示詞與風格生成一張場景插圖，回傳解碼後的 PNG 位元組
+    pub async fn generate_image(&self, prompt: &str, style: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let request_data = json!({
+            "prompt": prompt,
+            "style": style
+        });
+
+        let response = self.make_request("/ai/generate_image", Some(request_data)).await?;
+
+        let base64_png = response["image_base64"]
+            .as_str()
+            .ok_or("回應中缺少 image_base64 欄位")?;
+
+        let bytes = base64::engine::general_purpose::STANDARD.decode(base64_png)?;
+        Ok(bytes)
+    }
+
+    // 文字轉語音：將文本以指定語音合成為音訊（MP3/OGG）位元組
+    pub async fn synthesize_speech(&self, text: &str, voice: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let request_data = json!({
+            "text": text,
+            "voice": voice
+        });
+
+        let response = self.make_request("/ai/tts", Some(request_data)).await?;
+
+        let base64_audio = response["audio_base64"]
+            .as_str()
+            .ok_or("回應中缺少 audio_base64 欄位")?;
+
+        let bytes = base64::engine::general_purpose::STANDARD.decode(base64_audio)?;
+        Ok(bytes)
+    }
+
     // 健康檢查
     pub async fn health_check(&self) -> Result<String, Box<dyn std::error::Error>> {
         let response = self.make_request("/health", None).await?;
@@ -98,6 +464,21 @@ impl ModernReaderLinuxSDK {
 
     // 私有方法：發送請求
     async fn make_request(&self, endpoint: &str, data: Option<Value>) -> Result<Value, Box<dyn std::error::Error>> {
+        let response = self.send_request(endpoint, data.clone()).await?;
+
+        // 401 時嘗試以 refresh token 換發新憑證後重試一次
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED && self.refresh_session().await.unwrap_or(false) {
+            let response = self.send_request(endpoint, data).await?;
+            let json: Value = response.json().await?;
+            return Ok(json);
+        }
+
+        let json: Value = response.json().await?;
+        Ok(json)
+    }
+
+    // 私有方法：組裝並送出單次 HTTP 請求（不處理重試）
+    async fn send_request(&self, endpoint: &str, data: Option<Value>) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
         let url = format!("{}{}", self.base_url, endpoint);
         let mut request = self.client.post(&url);
 
@@ -111,15 +492,117 @@ impl ModernReaderLinuxSDK {
             request = request.json(&data);
         }
 
-        let response = request.send().await?;
-        let json: Value = response.json().await?;
-        
-        Ok(json)
+        Ok(request.send().await?)
+    }
+
+    // 私有方法：組裝並送出單次 SSE 串流請求（不處理重試），供 enhance_text_stream 重試時共用
+    async fn send_stream_request(&self, url: &str, request_data: &Value) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        let mut request = self
+            .client
+            .post(url)
+            .header("Accept", "text/event-stream")
+            .json(request_data);
+
+        if let Some(token) = &*self.session_token.lock().await {
+            request = request.bearer_auth(token);
+        }
+
+        Ok(request.send().await?)
+    }
+}
+
+// MARK: - 歷史紀錄子系統
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    pub input: String,
+    pub style: String,
+    pub operation: String,
+    pub output: String,
+    pub emotion: Option<String>,
+    pub confidence: Option<f64>,
+    pub timestamp: i64,
+}
+
+// 將每次增強/分析結果以 JSON Lines 記錄在本機，供離線瀏覽
+pub struct HistorySession {
+    path: std::path::PathBuf,
+}
+
+impl HistorySession {
+    pub fn new() -> Self {
+        let dir = Self::data_dir().join("modern-reader");
+        Self {
+            path: dir.join("history.jsonl"),
+        }
+    }
+
+    fn data_dir() -> std::path::PathBuf {
+        if let Ok(xdg) = std::env::var("XDG_DATA_HOME") {
+            std::path::PathBuf::from(xdg)
+        } else {
+            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+            std::path::PathBuf::from(home).join(".local/share")
+        }
+    }
+
+    // 將一筆紀錄以 JSON Lines 附加寫入歷史檔案
+    pub fn save_entry(&self, entry: &HistoryEntry) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        use std::io::Write;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    // 讀取所有歷史紀錄，依寫入順序排列
+    pub fn load_history(&self) -> std::io::Result<Vec<HistoryEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = std::fs::read_to_string(&self.path)?;
+        let entries = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok())
+            .collect();
+
+        Ok(entries)
+    }
+
+    // 將歷史紀錄匯出為 Markdown 文件
+    pub fn export_markdown(&self) -> std::io::Result<String> {
+        let entries = self.load_history()?;
+        let mut markdown = String::from("# Modern Reader 歷史紀錄\n\n");
+
+        for entry in &entries {
+            markdown.push_str(&format!("## {} - {}\n\n", entry.operation, entry.timestamp));
+            markdown.push_str(&format!("**輸入**\n\n```\n{}\n```\n\n", entry.input));
+            markdown.push_str(&format!("**結果**\n\n```\n{}\n```\n\n", entry.output));
+            if let Some(emotion) = &entry.emotion {
+                markdown.push_str(&format!(
+                    "**情感**: {} ({:.1}%)\n\n",
+                    emotion,
+                    entry.confidence.unwrap_or(0.0) * 100.0
+                ));
+            }
+        }
+
+        Ok(markdown)
     }
 }
 
 // MARK: - GTK4 應用程式
 fn main() -> glib::ExitCode {
+    gst::init().expect("GStreamer 初始化失敗");
+
     let app = Application::builder()
         .application_id("com.modernreader.linux")
         .build();
@@ -132,11 +615,28 @@ fn build_ui(app: &Application) {
     let window = ApplicationWindow::builder()
         .application(app)
         .title("Modern Reader - 現代閱讀器")
-        .default_width(900)
+        .default_width(1150)
         .default_height(700)
         .build();
 
     let sdk = ModernReaderLinuxSDK::new();
+    let history = Arc::new(HistorySession::new());
+
+    // 根容器：側邊欄歷史紀錄 + 主要內容
+    let root_box = Box::new(gtk4::Orientation::Horizontal, 12);
+
+    // 側邊欄：歷史紀錄
+    let history_frame = gtk4::Frame::new(Some("歷史紀錄"));
+    history_frame.set_size_request(240, -1);
+    let history_sidebar_box = Box::new(gtk4::Orientation::Vertical, 8);
+    let history_scroll = gtk4::ScrolledWindow::new();
+    history_scroll.set_vexpand(true);
+    let history_list = gtk4::ListBox::new();
+    history_scroll.set_child(Some(&history_list));
+    let export_history_button = Button::with_label("匯出 Markdown");
+    history_sidebar_box.append(&history_scroll);
+    history_sidebar_box.append(&export_history_button);
+    history_frame.set_child(Some(&history_sidebar_box));
 
     // 主要容器
     let main_box = Box::new(gtk4::Orientation::Vertical, 12);
@@ -180,8 +680,14 @@ fn build_ui(app: &Application) {
     style_box.append(&style_label);
     style_box.append(&style_combo);
 
+    // Token 計數標籤
+    let token_count_label = Label::new(Some(&format!("0 / {} tokens", MAX_CONTEXT_TOKENS)));
+    token_count_label.add_css_class("dim-label");
+    token_count_label.set_halign(gtk4::Align::End);
+
     input_box.append(&text_view);
     input_box.append(&style_box);
+    input_box.append(&token_count_label);
     input_frame.set_child(Some(&input_box));
 
     // 按鈕區域
@@ -194,14 +700,41 @@ fn build_ui(app: &Application) {
     let analyze_button = Button::with_label("😊 情感分析");
     
     let health_button = Button::with_label("🏥 健康檢查");
-    
+
+    let illustrate_button = Button::with_label("🎨 生成插圖");
+
+    let voice_combo = gtk4::ComboBoxText::new();
+    voice_combo.append_text("zh-TW-female");
+    voice_combo.append_text("zh-TW-male");
+    voice_combo.append_text("en-US-female");
+    voice_combo.set_active(Some(0));
+
+    let narrate_button = Button::with_label("🔊 朗讀");
+
+    let compare_button = Button::with_label("🔬 多模型比較");
+
+    let open_document_button = Button::with_label("📂 開啟文件");
+    let save_document_button = Button::with_label("💾 儲存文件");
+    save_document_button.set_sensitive(false);
+
     let progress_bar = ProgressBar::new();
     progress_bar.set_visible(false);
 
+    let document_progress_label = Label::new(None);
+    document_progress_label.add_css_class("dim-label");
+    document_progress_label.set_visible(false);
+
     button_box.append(&enhance_button);
     button_box.append(&analyze_button);
     button_box.append(&health_button);
+    button_box.append(&illustrate_button);
+    button_box.append(&voice_combo);
+    button_box.append(&narrate_button);
+    button_box.append(&compare_button);
+    button_box.append(&open_document_button);
+    button_box.append(&save_document_button);
     button_box.append(&progress_bar);
+    button_box.append(&document_progress_label);
 
     // 結果區域
     let result_frame = gtk4::Frame::new(Some("結果"));
@@ -213,21 +746,166 @@ fn build_ui(app: &Application) {
     result_buffer.set_text("尚無結果");
     result_frame.set_child(Some(&result_text_view));
 
+    // 插圖區域
+    let illustration_frame = gtk4::Frame::new(Some("插圖"));
+    illustration_frame.set_margin_top(16);
+    let illustration_box = Box::new(gtk4::Orientation::Vertical, 8);
+    illustration_box.set_margin_top(12);
+    illustration_box.set_margin_bottom(12);
+    illustration_box.set_margin_start(12);
+    illustration_box.set_margin_end(12);
+
+    let illustration_picture = gtk4::Picture::new();
+    illustration_picture.set_height_request(300);
+    illustration_picture.set_content_fit(gtk4::ContentFit::Contain);
+
+    let save_image_button = Button::with_label("💾 儲存插圖");
+    save_image_button.set_sensitive(false);
+
+    illustration_box.append(&illustration_picture);
+    illustration_box.append(&save_image_button);
+    illustration_frame.set_child(Some(&illustration_box));
+
+    let illustration_bytes: Rc<std::cell::RefCell<Option<Vec<u8>>>> = Rc::new(std::cell::RefCell::new(None));
+
+    // 朗讀播放狀態：目前的 playbin、它正在播放的文本（用來判斷是否需要重新合成），
+    // 以及它背後的暫存音訊檔路徑（汰換或播放結束時需一併刪除，避免留下孤兒檔案）
+    let playbin_state: Rc<std::cell::RefCell<Option<gst::Element>>> = Rc::new(std::cell::RefCell::new(None));
+    let playbin_text: Rc<std::cell::RefCell<Option<String>>> = Rc::new(std::cell::RefCell::new(None));
+    let playbin_path: Rc<std::cell::RefCell<Option<std::path::PathBuf>>> = Rc::new(std::cell::RefCell::new(None));
+
+    // 批次文件增強：重組後的文件內容，待使用者另存新檔
+    let enhanced_document: Rc<std::cell::RefCell<Option<String>>> = Rc::new(std::cell::RefCell::new(None));
+
+    // 多模型比較區域：每個模型各一張結果卡片
+    const COMPARISON_MODELS: &[&str] = &["gpt-3.5-turbo", "gpt-4", "claude-3"];
+
+    let comparison_frame = gtk4::Frame::new(Some("多模型比較"));
+    comparison_frame.set_margin_top(16);
+    comparison_frame.set_visible(false);
+    let comparison_box = Box::new(gtk4::Orientation::Horizontal, 12);
+    comparison_box.set_margin_top(12);
+    comparison_box.set_margin_bottom(12);
+    comparison_box.set_margin_start(12);
+    comparison_box.set_margin_end(12);
+    comparison_frame.set_child(Some(&comparison_box));
+
     // 狀態欄
     let status_box = Box::new(gtk4::Orientation::Horizontal, 8);
     status_box.set_margin_top(16);
     let status_label = Label::new(Some("狀態: 未連接"));
     status_label.add_css_class("dim-label");
+
+    let auth_status_label = Label::new(Some("未登入"));
+    auth_status_label.add_css_class("dim-label");
+
+    let login_button = Button::with_label("🔑 登入");
+    let logout_button = Button::with_label("登出");
+    logout_button.set_visible(false);
+
     status_box.append(&status_label);
+    status_box.append(&auth_status_label);
+    status_box.append(&login_button);
+    status_box.append(&logout_button);
 
     // 組裝界面
     main_box.append(&title_box);
     main_box.append(&input_frame);
     main_box.append(&button_box);
     main_box.append(&result_frame);
+    main_box.append(&comparison_frame);
+    main_box.append(&illustration_frame);
     main_box.append(&status_box);
 
-    window.set_child(Some(&main_box));
+    root_box.append(&history_frame);
+    root_box.append(&main_box);
+    window.set_child(Some(&root_box));
+
+    // 填充歷史紀錄側邊欄；點擊時還原輸入與結果
+    let refresh_history_list: Rc<dyn Fn()> = {
+        let history = history.clone();
+        let history_list = history_list.clone();
+        Rc::new(move || {
+            while let Some(row) = history_list.row_at_index(0) {
+                history_list.remove(&row);
+            }
+
+            let entries = history.load_history().unwrap_or_default();
+            for entry in entries.into_iter().rev() {
+                let preview: String = entry.input.chars().take(24).collect();
+                let row_label = Label::new(Some(&format!("[{}] {}", entry.operation, preview)));
+                row_label.set_halign(gtk4::Align::Start);
+                row_label.set_margin_top(4);
+                row_label.set_margin_bottom(4);
+                row_label.set_margin_start(8);
+                row_label.set_margin_end(8);
+
+                let row = gtk4::ListBoxRow::new();
+                row.set_child(Some(&row_label));
+                history_list.append(&row);
+            }
+        })
+    };
+    refresh_history_list();
+
+    let text_buffer_for_history = text_buffer.clone();
+    let result_buffer_for_history = result_buffer.clone();
+    let history_for_click = history.clone();
+    history_list.connect_row_activated(move |_, row| {
+        let index = row.index();
+        if let Ok(entries) = history_for_click.load_history() {
+            let entries: Vec<_> = entries.into_iter().rev().collect();
+            if let Some(entry) = entries.get(index as usize) {
+                text_buffer_for_history.set_text(&entry.input);
+                result_buffer_for_history.set_text(&entry.output);
+            }
+        }
+    });
+
+    // 匯出 Markdown 按鈕：將歷史紀錄匯出成 Markdown 檔案
+    let history_for_export = history.clone();
+    let window_clone = window.clone();
+    export_history_button.connect_clicked(move |_| {
+        let history = history_for_export.clone();
+        let window = window_clone.clone();
+
+        let Ok(markdown) = history.export_markdown() else {
+            return;
+        };
+
+        let dialog = gtk4::FileChooserDialog::new(
+            Some("匯出歷史紀錄"),
+            Some(&window),
+            gtk4::FileChooserAction::Save,
+            &[
+                ("取消", gtk4::ResponseType::Cancel),
+                ("匯出", gtk4::ResponseType::Accept),
+            ],
+        );
+        dialog.set_current_name("modern-reader-history.md");
+
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk4::ResponseType::Accept {
+                if let Some(file) = dialog.file() {
+                    if let Some(path) = file.path() {
+                        let _ = std::fs::write(path, &markdown);
+                    }
+                }
+            }
+            dialog.close();
+        });
+
+        dialog.show();
+    });
+
+    // 即時更新 token 計數
+    let sdk_clone = sdk.clone();
+    let token_count_label_clone = token_count_label.clone();
+    text_buffer.connect_changed(move |buffer| {
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let count = sdk_clone.count_tokens(&text);
+        token_count_label_clone.set_text(&format!("{} / {} tokens", count, MAX_CONTEXT_TOKENS));
+    });
 
     // 按鈕事件處理
     let sdk_clone = sdk.clone();
@@ -236,6 +914,8 @@ fn build_ui(app: &Application) {
     let style_combo_clone = style_combo.clone();
     let progress_bar_clone = progress_bar.clone();
     let enhance_button_clone = enhance_button.clone();
+    let history_clone = history.clone();
+    let refresh_history_list_clone = refresh_history_list.clone();
 
     enhance_button.connect_clicked(move |_| {
         let sdk = sdk_clone.clone();
@@ -244,31 +924,78 @@ fn build_ui(app: &Application) {
         let style_combo = style_combo_clone.clone();
         let progress_bar = progress_bar_clone.clone();
         let button = enhance_button_clone.clone();
+        let history = history_clone.clone();
+        let refresh_history_list = refresh_history_list_clone.clone();
 
         glib::spawn_future_local(async move {
             let text = text_buffer.text(&text_buffer.start_iter(), &text_buffer.end_iter(), false);
             if text.trim().is_empty() {
                 return;
             }
+            // 超過容量時從開頭截斷，保留最新的上下文
+            let text = sdk.truncate(&text, MAX_CONTEXT_TOKENS, TruncationDirection::Start);
 
             // 顯示進度條
             progress_bar.set_visible(true);
+            progress_bar.set_show_text(false);
             progress_bar.pulse();
             button.set_sensitive(false);
 
             let style = style_combo.active_text()
                 .map(|s| s.as_str().to_string())
                 .unwrap_or_else(|| "immersive".to_string());
+            let style_name = style.clone();
 
-            match sdk.enhance_text(&text, &style).await {
-                Ok(enhanced) => {
-                    result_buffer.set_text(&enhanced);
-                }
-                Err(err) => {
-                    result_buffer.set_text(&format!("錯誤: {}", err));
+            result_buffer.set_text("");
+
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<EnhanceStreamEvent>();
+            let sdk_stream = sdk.clone();
+            let text_stream = text.to_string();
+            glib::spawn_future_local(async move {
+                let _ = sdk_stream.enhance_text_stream(&text_stream, &style, tx).await;
+            });
+
+            let mut total_tokens: Option<usize> = None;
+            let mut received_tokens: usize = 0;
+            let mut received_any = false;
+
+            while let Some(event) = rx.recv().await {
+                match event {
+                    EnhanceStreamEvent::Delta(delta) => {
+                        received_any = true;
+                        received_tokens += 1;
+                        let mut end_iter = result_buffer.end_iter();
+                        result_buffer.insert(&mut end_iter, &delta);
+
+                        if let Some(total) = total_tokens {
+                            progress_bar.set_fraction(received_tokens as f64 / total.max(1) as f64);
+                        }
+                    }
+                    EnhanceStreamEvent::Total(total) => {
+                        total_tokens = Some(total);
+                        progress_bar.set_show_text(true);
+                        progress_bar.set_fraction(0.0);
+                    }
                 }
             }
 
+            if received_any {
+                let result_text = result_buffer.text(&result_buffer.start_iter(), &result_buffer.end_iter(), false);
+                let entry = HistoryEntry {
+                    input: text.to_string(),
+                    style: style_name,
+                    operation: "enhance".to_string(),
+                    output: result_text.to_string(),
+                    emotion: None,
+                    confidence: None,
+                    timestamp: now_unix_timestamp(),
+                };
+                let _ = history.save_entry(&entry);
+                refresh_history_list();
+            } else {
+                result_buffer.set_text("增強失敗");
+            }
+
             // 隱藏進度條
             progress_bar.set_visible(false);
             button.set_sensitive(true);
@@ -281,11 +1008,15 @@ fn build_ui(app: &Application) {
     let result_buffer_clone = result_buffer.clone();
     let progress_bar_clone = progress_bar.clone();
     let analyze_button_clone = analyze_button.clone();
+    let history_clone = history.clone();
+    let refresh_history_list_clone = refresh_history_list.clone();
 
     analyze_button.connect_clicked(move |_| {
         let sdk = sdk_clone.clone();
         let text_buffer = text_buffer_clone.clone();
         let result_buffer = result_buffer_clone.clone();
+        let history = history_clone.clone();
+        let refresh_history_list = refresh_history_list_clone.clone();
         let progress_bar = progress_bar_clone.clone();
         let button = analyze_button_clone.clone();
 
@@ -303,6 +1034,18 @@ fn build_ui(app: &Application) {
                 Ok((emotion, confidence)) => {
                     let result = format!("情感: {}\n信心度: {:.1}%", emotion, confidence * 100.0);
                     result_buffer.set_text(&result);
+
+                    let entry = HistoryEntry {
+                        input: text.to_string(),
+                        style: String::new(),
+                        operation: "analyze_emotion".to_string(),
+                        output: result,
+                        emotion: Some(emotion),
+                        confidence: Some(confidence),
+                        timestamp: now_unix_timestamp(),
+                    };
+                    let _ = history.save_entry(&entry);
+                    refresh_history_list();
                 }
                 Err(err) => {
                     result_buffer.set_text(&format!("錯誤: {}", err));
@@ -334,6 +1077,569 @@ fn build_ui(app: &Application) {
         });
     });
 
+    // 生成插圖按鈕：以增強結果（或輸入文本）作為提示詞
+    let sdk_clone = sdk.clone();
+    let text_buffer_clone = text_buffer.clone();
+    let result_buffer_clone = result_buffer.clone();
+    let style_combo_clone = style_combo.clone();
+    let progress_bar_clone = progress_bar.clone();
+    let illustrate_button_clone = illustrate_button.clone();
+    let illustration_picture_clone = illustration_picture.clone();
+    let save_image_button_clone = save_image_button.clone();
+    let illustration_bytes_clone = illustration_bytes.clone();
+
+    illustrate_button.connect_clicked(move |_| {
+        let sdk = sdk_clone.clone();
+        let text_buffer = text_buffer_clone.clone();
+        let result_buffer = result_buffer_clone.clone();
+        let style_combo = style_combo_clone.clone();
+        let progress_bar = progress_bar_clone.clone();
+        let button = illustrate_button_clone.clone();
+        let picture = illustration_picture_clone.clone();
+        let save_button = save_image_button_clone.clone();
+        let illustration_bytes = illustration_bytes_clone.clone();
+
+        glib::spawn_future_local(async move {
+            let result_text = result_buffer.text(&result_buffer.start_iter(), &result_buffer.end_iter(), false);
+            let input_text = text_buffer.text(&text_buffer.start_iter(), &text_buffer.end_iter(), false);
+            let prompt = if result_text.trim().is_empty() || result_text == "尚無結果" {
+                input_text.to_string()
+            } else {
+                result_text.to_string()
+            };
+            if prompt.trim().is_empty() {
+                return;
+            }
+
+            let style = style_combo.active_text()
+                .map(|s| s.as_str().to_string())
+                .unwrap_or_else(|| "immersive".to_string());
+
+            progress_bar.set_visible(true);
+            progress_bar.pulse();
+            button.set_sensitive(false);
+            save_button.set_sensitive(false);
+
+            match sdk.generate_image(&prompt, &style).await {
+                Ok(bytes) => match load_pixbuf_from_bytes(&bytes) {
+                    Ok(pixbuf) => {
+                        picture.set_pixbuf(Some(&pixbuf));
+                        *illustration_bytes.borrow_mut() = Some(bytes);
+                        save_button.set_sensitive(true);
+                    }
+                    Err(_) => {
+                        picture.set_paintable(gtk4::gdk::Paintable::NONE);
+                    }
+                },
+                Err(_) => {
+                    picture.set_paintable(gtk4::gdk::Paintable::NONE);
+                }
+            }
+
+            progress_bar.set_visible(false);
+            button.set_sensitive(true);
+        });
+    });
+
+    // 儲存插圖按鈕
+    let illustration_bytes_clone = illustration_bytes.clone();
+    let window_clone = window.clone();
+    save_image_button.connect_clicked(move |_| {
+        let illustration_bytes = illustration_bytes_clone.clone();
+        let window = window_clone.clone();
+
+        let Some(bytes) = illustration_bytes.borrow().clone() else {
+            return;
+        };
+
+        let dialog = gtk4::FileChooserDialog::new(
+            Some("儲存插圖"),
+            Some(&window),
+            gtk4::FileChooserAction::Save,
+            &[
+                ("取消", gtk4::ResponseType::Cancel),
+                ("儲存", gtk4::ResponseType::Accept),
+            ],
+        );
+        dialog.set_current_name("illustration.png");
+
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk4::ResponseType::Accept {
+                if let Some(file) = dialog.file() {
+                    if let Some(path) = file.path() {
+                        let _ = std::fs::write(path, &bytes);
+                    }
+                }
+            }
+            dialog.close();
+        });
+
+        dialog.show();
+    });
+
+    // 朗讀按鈕：合成語音並透過 GStreamer playbin 播放，支援播放/暫停切換
+    let sdk_clone = sdk.clone();
+    let text_buffer_clone = text_buffer.clone();
+    let result_buffer_clone = result_buffer.clone();
+    let voice_combo_clone = voice_combo.clone();
+    let status_label_clone = status_label.clone();
+    let narrate_button_clone = narrate_button.clone();
+    let playbin_state_clone = playbin_state.clone();
+    let playbin_text_clone = playbin_text.clone();
+    let playbin_path_clone = playbin_path.clone();
+
+    narrate_button.connect_clicked(move |_| {
+        let sdk = sdk_clone.clone();
+        let text_buffer = text_buffer_clone.clone();
+        let result_buffer = result_buffer_clone.clone();
+        let voice_combo = voice_combo_clone.clone();
+        let status_label = status_label_clone.clone();
+        let button = narrate_button_clone.clone();
+        let playbin_state = playbin_state_clone.clone();
+        let playbin_text = playbin_text_clone.clone();
+        let playbin_path = playbin_path_clone.clone();
+
+        glib::spawn_future_local(async move {
+            let result_text = result_buffer.text(&result_buffer.start_iter(), &result_buffer.end_iter(), false);
+            let input_text = text_buffer.text(&text_buffer.start_iter(), &text_buffer.end_iter(), false);
+            let text = if result_text.trim().is_empty() || result_text == "尚無結果" {
+                input_text.to_string()
+            } else {
+                result_text.to_string()
+            };
+            if text.trim().is_empty() {
+                return;
+            }
+
+            // 若目前已有針對相同文本播放中的 playbin，僅切換播放/暫停
+            let existing_playbin = playbin_state.borrow().clone();
+            if let Some(playbin) = existing_playbin {
+                if playbin_text.borrow().as_deref() == Some(text.as_str()) {
+                    let (_, current, _) = playbin.state(gst::ClockTime::NONE);
+                    if current == gst::State::Playing {
+                        let _ = playbin.set_state(gst::State::Paused);
+                        button.set_label("▶ 繼續");
+                        status_label.set_text("狀態: 已暫停");
+                    } else {
+                        let _ = playbin.set_state(gst::State::Playing);
+                        button.set_label("⏸ 暫停");
+                        status_label.set_text("狀態: 播放中");
+                    }
+                    return;
+                }
+
+                // 文本已變更：停止並釋放舊的 playbin，刪除其暫存音訊檔後重新合成
+                let _ = playbin.set_state(gst::State::Null);
+                *playbin_state.borrow_mut() = None;
+                *playbin_text.borrow_mut() = None;
+                if let Some(old_path) = playbin_path.borrow_mut().take() {
+                    let _ = std::fs::remove_file(old_path);
+                }
+            }
+
+            let voice = voice_combo.active_text()
+                .map(|s| s.as_str().to_string())
+                .unwrap_or_else(|| "zh-TW-female".to_string());
+
+            button.set_sensitive(false);
+            status_label.set_text("狀態: 合成語音中...");
+
+            match sdk.synthesize_speech(&text, &voice).await {
+                Ok(audio_bytes) => {
+                    let temp_path = std::env::temp_dir()
+                        .join(format!("modern-reader-tts-{}.mp3", now_unix_timestamp()));
+
+                    if std::fs::write(&temp_path, &audio_bytes).is_ok() {
+                        match gst::ElementFactory::make("playbin").build() {
+                            Ok(playbin) => {
+                                playbin.set_property("uri", format!("file://{}", temp_path.display()));
+                                let _ = playbin.set_state(gst::State::Playing);
+
+                                // EOS/錯誤時清空播放狀態並刪除暫存音訊檔，讓下一次點擊重新合成而非卡在已結束的 playbin 上
+                                let playbin_state_for_bus = playbin_state.clone();
+                                let playbin_text_for_bus = playbin_text.clone();
+                                let playbin_path_for_bus = playbin_path.clone();
+                                let button_for_bus = button.clone();
+                                let status_label_for_bus = status_label.clone();
+                                if let Some(bus) = playbin.bus() {
+                                    let watch = bus.add_watch_local(move |_, msg| {
+                                        match msg.view() {
+                                            gst::MessageView::Eos(_) | gst::MessageView::Error(_) => {
+                                                if let Some(old) = playbin_state_for_bus.borrow_mut().take() {
+                                                    let _ = old.set_state(gst::State::Null);
+                                                }
+                                                *playbin_text_for_bus.borrow_mut() = None;
+                                                if let Some(old_path) = playbin_path_for_bus.borrow_mut().take() {
+                                                    let _ = std::fs::remove_file(old_path);
+                                                }
+                                                button_for_bus.set_label("🔊 朗讀");
+                                                status_label_for_bus.set_text("狀態: 播放結束");
+                                                // 播放已結束：回傳 Break 讓 GLib 移除這個 watch
+                                                glib::ControlFlow::Break
+                                            }
+                                            _ => glib::ControlFlow::Continue,
+                                        }
+                                    });
+                                    // 丟棄 add_watch_local 回傳的 guard 會立即移除 watch，因此不丟棄它；watch 在上方回傳 Break 時移除
+                                    if let Ok(watch) = watch {
+                                        std::mem::forget(watch);
+                                    }
+                                }
+
+                                *playbin_state.borrow_mut() = Some(playbin);
+                                *playbin_text.borrow_mut() = Some(text);
+                                *playbin_path.borrow_mut() = Some(temp_path);
+                                button.set_label("⏸ 暫停");
+                                status_label.set_text("狀態: 播放中");
+                            }
+                            Err(_) => {
+                                let _ = std::fs::remove_file(&temp_path);
+                                status_label.set_text("狀態: 無法建立播放器");
+                            }
+                        }
+                    } else {
+                        status_label.set_text("狀態: 音訊寫入失敗");
+                    }
+                }
+                Err(err) => {
+                    status_label.set_text(&format!("狀態: 朗讀失敗 ({})", err));
+                }
+            }
+
+            button.set_sensitive(true);
+        });
+    });
+
+    // 多模型比較按鈕：同時比較各後端模型的增強結果
+    let sdk_clone = sdk.clone();
+    let text_buffer_clone = text_buffer.clone();
+    let result_buffer_clone = result_buffer.clone();
+    let style_combo_clone = style_combo.clone();
+    let progress_bar_clone = progress_bar.clone();
+    let compare_button_clone = compare_button.clone();
+    let comparison_frame_clone = comparison_frame.clone();
+    let comparison_box_clone = comparison_box.clone();
+
+    compare_button.connect_clicked(move |_| {
+        let sdk = sdk_clone.clone();
+        let text_buffer = text_buffer_clone.clone();
+        let result_buffer = result_buffer_clone.clone();
+        let style_combo = style_combo_clone.clone();
+        let progress_bar = progress_bar_clone.clone();
+        let button = compare_button_clone.clone();
+        let comparison_frame = comparison_frame_clone.clone();
+        let comparison_box = comparison_box_clone.clone();
+
+        glib::spawn_future_local(async move {
+            let text = text_buffer.text(&text_buffer.start_iter(), &text_buffer.end_iter(), false);
+            if text.trim().is_empty() {
+                return;
+            }
+
+            let style = style_combo.active_text()
+                .map(|s| s.as_str().to_string())
+                .unwrap_or_else(|| "immersive".to_string());
+
+            progress_bar.set_visible(true);
+            progress_bar.pulse();
+            button.set_sensitive(false);
+
+            while let Some(child) = comparison_box.first_child() {
+                comparison_box.remove(&child);
+            }
+
+            let results = sdk.enhance_text_multi(&text, &style, COMPARISON_MODELS).await;
+
+            for (model, result) in results {
+                let card_frame = gtk4::Frame::new(Some(&model));
+                let card_box = Box::new(gtk4::Orientation::Vertical, 8);
+                card_box.set_margin_top(8);
+                card_box.set_margin_bottom(8);
+                card_box.set_margin_start(8);
+                card_box.set_margin_end(8);
+
+                let card_view = TextView::new();
+                card_view.set_editable(false);
+                card_view.set_height_request(160);
+                let card_buffer = card_view.buffer();
+
+                let adopt_button = Button::with_label("採用");
+
+                match result {
+                    Ok(text) => {
+                        card_buffer.set_text(&text);
+
+                        let result_buffer = result_buffer.clone();
+                        let card_buffer_clone = card_buffer.clone();
+                        adopt_button.connect_clicked(move |_| {
+                            let text = card_buffer_clone.text(
+                                &card_buffer_clone.start_iter(),
+                                &card_buffer_clone.end_iter(),
+                                false,
+                            );
+                            result_buffer.set_text(&text);
+                        });
+                    }
+                    Err(err) => {
+                        card_buffer.set_text(&format!("錯誤: {}", err));
+                        adopt_button.set_sensitive(false);
+                    }
+                }
+
+                card_box.append(&card_view);
+                card_box.append(&adopt_button);
+                card_frame.set_child(Some(&card_box));
+                comparison_box.append(&card_frame);
+            }
+
+            comparison_frame.set_visible(true);
+            progress_bar.set_visible(false);
+            button.set_sensitive(true);
+        });
+    });
+
+    // 開啟文件按鈕：讀取文字/Markdown 檔案，依空行切分為段落並批次增強
+    let sdk_clone = sdk.clone();
+    let window_clone = window.clone();
+    let style_combo_clone = style_combo.clone();
+    let progress_bar_clone = progress_bar.clone();
+    let document_progress_label_clone = document_progress_label.clone();
+    let open_document_button_clone = open_document_button.clone();
+    let save_document_button_clone = save_document_button.clone();
+    let enhanced_document_clone = enhanced_document.clone();
+
+    open_document_button.connect_clicked(move |_| {
+        let sdk = sdk_clone.clone();
+        let style_combo = style_combo_clone.clone();
+        let progress_bar = progress_bar_clone.clone();
+        let document_progress_label = document_progress_label_clone.clone();
+        let button = open_document_button_clone.clone();
+        let save_document_button = save_document_button_clone.clone();
+        let enhanced_document = enhanced_document_clone.clone();
+
+        let dialog = gtk4::FileChooserDialog::new(
+            Some("開啟文件"),
+            Some(&window_clone),
+            gtk4::FileChooserAction::Open,
+            &[
+                ("取消", gtk4::ResponseType::Cancel),
+                ("開啟", gtk4::ResponseType::Accept),
+            ],
+        );
+
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk4::ResponseType::Accept {
+                if let Some(file) = dialog.file() {
+                    if let Some(path) = file.path() {
+                        let sdk = sdk.clone();
+                        let style_combo = style_combo.clone();
+                        let progress_bar = progress_bar.clone();
+                        let document_progress_label = document_progress_label.clone();
+                        let button = button.clone();
+                        let save_document_button = save_document_button.clone();
+                        let enhanced_document = enhanced_document.clone();
+
+                        glib::spawn_future_local(async move {
+                            let Ok(content) = std::fs::read_to_string(&path) else {
+                                return;
+                            };
+
+                            let paragraphs: Vec<String> = content
+                                .split("\n\n")
+                                .map(|p| p.trim().to_string())
+                                .filter(|p| !p.is_empty())
+                                .collect();
+
+                            if paragraphs.is_empty() {
+                                return;
+                            }
+
+                            let style = style_combo.active_text()
+                                .map(|s| s.as_str().to_string())
+                                .unwrap_or_else(|| "immersive".to_string());
+
+                            button.set_sensitive(false);
+                            save_document_button.set_sensitive(false);
+                            progress_bar.set_visible(true);
+                            progress_bar.set_show_text(true);
+                            progress_bar.set_fraction(0.0);
+                            document_progress_label.set_visible(true);
+
+                            let total = paragraphs.len();
+                            let original_paragraphs = paragraphs.clone();
+                            let progress_bar_for_callback = progress_bar.clone();
+                            let document_progress_label_for_callback = document_progress_label.clone();
+
+                            let results = sdk.enhance_document(paragraphs, &style, move |done, total| {
+                                progress_bar_for_callback.set_fraction(done as f64 / total.max(1) as f64);
+                                document_progress_label_for_callback.set_text(&format!("{}/{} 段落", done, total));
+                            }).await;
+
+                            // 逐段落保留結果：失敗的段落落回原文，而非讓一個段落的錯誤丟棄整份已完成的增強
+                            let failed = results.iter().filter(|r| r.is_err()).count();
+                            let enhanced_paragraphs: Vec<String> = results
+                                .into_iter()
+                                .zip(original_paragraphs.iter())
+                                .map(|(result, original)| result.unwrap_or_else(|_| original.clone()))
+                                .collect();
+
+                            let document = enhanced_paragraphs.join("\n\n");
+                            *enhanced_document.borrow_mut() = Some(document);
+                            save_document_button.set_sensitive(true);
+                            if failed == 0 {
+                                document_progress_label.set_text(&format!("{}/{} 段落完成", total, total));
+                            } else {
+                                document_progress_label.set_text(&format!("{}/{} 段落完成，{} 段落增強失敗（已保留原文）", total - failed, total, failed));
+                            }
+
+                            progress_bar.set_visible(false);
+                            button.set_sensitive(true);
+                        });
+                    }
+                }
+            }
+            dialog.close();
+        });
+
+        dialog.show();
+    });
+
+    // 儲存文件按鈕：將重組後的增強文件另存新檔
+    let window_clone = window.clone();
+    let enhanced_document_clone = enhanced_document.clone();
+    save_document_button.connect_clicked(move |_| {
+        let enhanced_document = enhanced_document_clone.clone();
+        let window = window_clone.clone();
+
+        let Some(document) = enhanced_document.borrow().clone() else {
+            return;
+        };
+
+        let dialog = gtk4::FileChooserDialog::new(
+            Some("儲存文件"),
+            Some(&window),
+            gtk4::FileChooserAction::Save,
+            &[
+                ("取消", gtk4::ResponseType::Cancel),
+                ("儲存", gtk4::ResponseType::Accept),
+            ],
+        );
+        dialog.set_current_name("enhanced-document.md");
+
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk4::ResponseType::Accept {
+                if let Some(file) = dialog.file() {
+                    if let Some(path) = file.path() {
+                        let _ = std::fs::write(path, &document);
+                    }
+                }
+            }
+            dialog.close();
+        });
+
+        dialog.show();
+    });
+
+    // 登入按鈕：開啟登入對話框
+    let sdk_clone = sdk.clone();
+    let window_clone = window.clone();
+    let login_button_clone = login_button.clone();
+    let logout_button_clone = logout_button.clone();
+    let auth_status_label_clone = auth_status_label.clone();
+
+    login_button.connect_clicked(move |_| {
+        let sdk = sdk_clone.clone();
+        let login_button = login_button_clone.clone();
+        let logout_button = logout_button_clone.clone();
+        let auth_status_label = auth_status_label_clone.clone();
+
+        let dialog = gtk4::Dialog::with_buttons(
+            Some("登入"),
+            Some(&window_clone),
+            gtk4::DialogFlags::MODAL,
+            &[
+                ("取消", gtk4::ResponseType::Cancel),
+                ("登入", gtk4::ResponseType::Accept),
+            ],
+        );
+
+        let form_box = Box::new(gtk4::Orientation::Vertical, 8);
+        form_box.set_margin_top(12);
+        form_box.set_margin_bottom(12);
+        form_box.set_margin_start(12);
+        form_box.set_margin_end(12);
+
+        let identifier_entry = Entry::new();
+        identifier_entry.set_placeholder_text(Some("帳號 / Email"));
+        let password_entry = Entry::new();
+        password_entry.set_placeholder_text(Some("密碼"));
+        password_entry.set_visibility(false);
+
+        form_box.append(&identifier_entry);
+        form_box.append(&password_entry);
+        dialog.content_area().append(&form_box);
+
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk4::ResponseType::Accept {
+                let sdk = sdk.clone();
+                let login_button = login_button.clone();
+                let logout_button = logout_button.clone();
+                let auth_status_label = auth_status_label.clone();
+                let identifier = identifier_entry.text().to_string();
+                let password = password_entry.text().to_string();
+
+                glib::spawn_future_local(async move {
+                    match sdk.login(&identifier, &password).await {
+                        Ok(true) => {
+                            auth_status_label.set_text("已登入");
+                            login_button.set_visible(false);
+                            logout_button.set_visible(true);
+                        }
+                        _ => {
+                            auth_status_label.set_text("登入失敗");
+                        }
+                    }
+                });
+            }
+            dialog.close();
+        });
+
+        dialog.present();
+    });
+
+    // 登出按鈕
+    let sdk_clone = sdk.clone();
+    let login_button_clone = login_button.clone();
+    let logout_button_clone = logout_button.clone();
+    let auth_status_label_clone = auth_status_label.clone();
+
+    logout_button.connect_clicked(move |_| {
+        let sdk = sdk_clone.clone();
+        let login_button = login_button_clone.clone();
+        let logout_button = logout_button_clone.clone();
+        let auth_status_label = auth_status_label_clone.clone();
+
+        glib::spawn_future_local(async move {
+            let _ = sdk.logout().await;
+            auth_status_label.set_text("未登入");
+            login_button.set_visible(true);
+            logout_button.set_visible(false);
+        });
+    });
+
+    // 嘗試還原先前登入的 session
+    let sdk_clone = sdk.clone();
+    let login_button_clone = login_button.clone();
+    let logout_button_clone = logout_button.clone();
+    let auth_status_label_clone = auth_status_label.clone();
+    glib::spawn_future_local(async move {
+        if sdk_clone.restore_session().await.unwrap_or(false) {
+            auth_status_label_clone.set_text("已登入");
+            login_button_clone.set_visible(false);
+            logout_button_clone.set_visible(true);
+        }
+    });
+
     // 初始健康檢查
     let sdk_clone = sdk.clone();
     let status_label_clone = status_label.clone();
@@ -358,4 +1664,10 @@ gtk4 = "0.7"
 reqwest = { version = "0.11", features = ["json"] }
 serde_json = "1.0"
 tokio = { version = "1.0", features = ["full"] }
+tiktoken-rs = "0.5"
+futures-util = "0.3"
+serde = { version = "1.0", features = ["derive"] }
+base64 = "0.21"
+gstreamer = "0.21"
+keyring = "2.0"
 */
\ No newline at end of file